@@ -0,0 +1,2 @@
+pub mod status_sort;
+pub mod status_summary;
@@ -14,6 +14,8 @@ use asyncgit::{
         extract_username_password, need_username_password,
         BasicAuthCredential,
     },
+    sync::get_remote_tracking_oid,
+    sync::remote_url_is_ssh,
     sync::DEFAULT_REMOTE_NAME,
     AsyncNotification, AsyncPush, PushProgress, PushProgressState,
     PushRequest,
@@ -23,11 +25,37 @@ use crossterm::event::Event;
 use tui::{
     backend::Backend,
     layout::Rect,
-    text::Span,
+    text::{Span, Spans},
     widgets::{Block, BorderType, Borders, Clear, Gauge},
     Frame,
 };
 
+/// a heuristic for "the remote rejected our credentials" vs. other
+/// push failures (network, non-fast-forward, ...), used to decide
+/// whether to retry with the next authentication method
+fn is_ssh_auth_rejected(err: &str) -> bool {
+    let err = err.to_ascii_lowercase();
+    err.contains("auth")
+        && (err.contains("ssh") || err.contains("publickey"))
+}
+
+/// authentication method attempted for the current push, threaded
+/// through to `AsyncPush`/`PushRequest`; after an ssh auth rejection
+/// `update` prompts for the key's passphrase and retries once, while a
+/// rejected `Basic` credential is reported as an error and not retried
+#[derive(Debug, Clone)]
+enum PushCredential {
+    /// `ssh-agent`, then a configured key file, optionally passphrase
+    /// protected via `CredComponent`
+    Ssh {
+        key_path: Option<String>,
+        passphrase: Option<String>,
+    },
+    /// the platform git credential helper, falling back to a
+    /// username/password prompt
+    Basic(BasicAuthCredential),
+}
+
 ///
 pub struct PushComponent {
     visible: bool,
@@ -35,6 +63,14 @@ pub struct PushComponent {
     progress: Option<PushProgress>,
     pending: bool,
     branch: String,
+    /// "force-with-lease": refuses to push unless the remote-tracking
+    /// ref still matches the oid we last saw, so we never clobber
+    /// commits we haven't fetched yet; the lease oid itself is
+    /// looked up fresh in `push_to_remote` right before pushing
+    force: bool,
+    set_upstream: bool,
+    ssh_key_path: Option<String>,
+    awaiting_ssh_passphrase: bool,
     queue: Queue,
     theme: SharedTheme,
     key_config: SharedKeyConfig,
@@ -54,6 +90,10 @@ impl PushComponent {
             pending: false,
             visible: false,
             branch: String::new(),
+            force: false,
+            set_upstream: false,
+            ssh_key_path: None,
+            awaiting_ssh_passphrase: false,
             git_push: AsyncPush::new(sender),
             progress: None,
             input_cred: CredComponent::new(
@@ -65,17 +105,34 @@ impl PushComponent {
         }
     }
 
+    /// configures the ssh private key file tried after `ssh-agent`
+    /// is unable to authenticate, e.g. from the user's gitui config
+    pub fn set_ssh_key_path(&mut self, ssh_key_path: Option<String>) {
+        self.ssh_key_path = ssh_key_path;
+    }
+
     ///
     pub fn push(&mut self, branch: String) -> Result<()> {
         self.branch = branch;
+        self.awaiting_ssh_passphrase = false;
         self.show()?;
-        if need_username_password(DEFAULT_REMOTE_NAME)? {
+
+        if remote_url_is_ssh(DEFAULT_REMOTE_NAME)? {
+            // first attempt: ssh-agent, no passphrase prompt yet
+            self.push_to_remote(Some(PushCredential::Ssh {
+                key_path: self.ssh_key_path.clone(),
+                passphrase: None,
+            }))
+        } else if need_username_password(DEFAULT_REMOTE_NAME)? {
+            // try the platform credential helper before prompting
             let cred = extract_username_password(DEFAULT_REMOTE_NAME)
                 .unwrap_or_else(|_| {
                     BasicAuthCredential::new(None, None)
                 });
             if cred.is_complete() {
-                self.push_to_remote(Some(cred))
+                self.push_to_remote(Some(PushCredential::Basic(
+                    cred,
+                )))
             } else {
                 self.input_cred.set_cred(cred);
                 self.input_cred.show()
@@ -87,19 +144,73 @@ impl PushComponent {
 
     fn push_to_remote(
         &mut self,
-        cred: Option<BasicAuthCredential>,
+        cred: Option<PushCredential>,
     ) -> Result<()> {
         self.pending = true;
         self.progress = None;
+
+        let (basic_credential, ssh_key_path, ssh_passphrase) =
+            match cred {
+                Some(PushCredential::Basic(basic)) => {
+                    (Some(basic), None, None)
+                }
+                Some(PushCredential::Ssh {
+                    key_path,
+                    passphrase,
+                }) => (None, key_path, passphrase),
+                None => (None, None, None),
+            };
+
+        // the lease: the oid `refs/remotes/<remote>/<branch>` pointed
+        // at as of right now. passed alongside `force` so the push is
+        // rejected if the remote moved since we last fetched, instead
+        // of blindly clobbering it like a bare `--force` would
+        let expected_oid = if self.force {
+            get_remote_tracking_oid(
+                DEFAULT_REMOTE_NAME,
+                &self.branch,
+            )
+            .ok()
+        } else {
+            None
+        };
+
         self.git_push.request(PushRequest {
-            //TODO: find tracking branch name
             remote: String::from(DEFAULT_REMOTE_NAME),
             branch: self.branch.clone(),
-            basic_credential: cred,
+            basic_credential,
+            ssh_key_path,
+            ssh_passphrase,
+            force: self.force,
+            expected_oid,
+            set_upstream: self.set_upstream,
         })?;
         Ok(())
     }
 
+    /// retries the push with the ssh key + passphrase the user just
+    /// entered in `input_cred`, after the agent-only attempt was
+    /// rejected
+    fn retry_with_ssh_passphrase(&mut self) -> Result<()> {
+        let passphrase = self
+            .input_cred
+            .get_cred()
+            .password()
+            .map(String::from);
+        self.push_to_remote(Some(PushCredential::Ssh {
+            key_path: self.ssh_key_path.clone(),
+            passphrase,
+        }))
+    }
+
+    fn toggle_force(&mut self) {
+        self.force = !self.force;
+    }
+
+    fn toggle_set_upstream(&mut self) {
+        self.set_upstream = !self.set_upstream;
+    }
+
     ///
     pub fn update_git(
         &mut self,
@@ -121,6 +232,20 @@ impl PushComponent {
 
         if !self.pending {
             if let Some(err) = self.git_push.last_result()? {
+                if is_ssh_auth_rejected(&err)
+                    && !self.awaiting_ssh_passphrase
+                {
+                    // agent (and bare key) rejected - ask for the
+                    // key's passphrase and retry once
+                    self.awaiting_ssh_passphrase = true;
+                    self.input_cred
+                        .set_cred(BasicAuthCredential::new(
+                            None, None,
+                        ));
+                    self.input_cred.show()?;
+                    return Ok(());
+                }
+
                 self.queue.borrow_mut().push_back(
                     InternalEvent::ShowErrorMsg(format!(
                         "push failed:\n{}",
@@ -160,6 +285,25 @@ impl PushComponent {
         }
         .into()
     }
+
+    fn mode_title(&self) -> Spans {
+        Spans::from(vec![
+            Span::styled(
+                strings::PUSH_POPUP_MSG,
+                self.theme.title(true),
+            ),
+            Span::raw(" "),
+            Span::styled(
+                strings::PUSH_POPUP_OPTION_FORCE,
+                self.theme.option(self.force),
+            ),
+            Span::raw(" "),
+            Span::styled(
+                strings::PUSH_POPUP_OPTION_SET_UPSTREAM,
+                self.theme.option(self.set_upstream),
+            ),
+        ])
+    }
 }
 
 impl DrawableComponent for PushComponent {
@@ -179,10 +323,7 @@ impl DrawableComponent for PushComponent {
                     .label(state.as_str())
                     .block(
                         Block::default()
-                            .title(Span::styled(
-                                strings::PUSH_POPUP_MSG,
-                                self.theme.title(true),
-                            ))
+                            .title(self.mode_title())
                             .borders(Borders::ALL)
                             .border_type(BorderType::Thick)
                             .border_style(self.theme.block(true)),
@@ -216,6 +357,18 @@ impl Component for PushComponent {
                 !self.pending,
                 self.visible,
             ));
+            out.push(CommandInfo::new(
+                strings::commands::push_force_toggle(self.force),
+                !self.pending,
+                self.visible,
+            ));
+            out.push(CommandInfo::new(
+                strings::commands::push_set_upstream_toggle(
+                    self.set_upstream,
+                ),
+                !self.pending,
+                self.visible,
+            ));
             visibility_blocking(self)
         }
     }
@@ -224,21 +377,41 @@ impl Component for PushComponent {
         if self.visible {
             if let Event::Key(e) = ev {
                 if e == self.key_config.exit_popup {
+                    // bail out of an in-progress passphrase prompt
+                    // and clear its "awaiting" state
+                    self.awaiting_ssh_passphrase = false;
                     self.hide();
                 }
                 if self.input_cred.event(ev)? {
                     return Ok(true);
                 } else if e == self.key_config.enter {
                     if self.input_cred.is_visible()
+                        && self.awaiting_ssh_passphrase
+                    {
+                        self.retry_with_ssh_passphrase()?;
+                        self.input_cred.hide();
+                    } else if self.input_cred.is_visible()
                         && self.input_cred.get_cred().is_complete()
                     {
                         self.push_to_remote(Some(
-                            self.input_cred.get_cred().clone(),
+                            PushCredential::Basic(
+                                self.input_cred.get_cred().clone(),
+                            ),
                         ))?;
                         self.input_cred.hide();
                     } else {
                         self.hide();
                     }
+                } else if !self.pending
+                    && !self.input_cred.is_visible()
+                    && e == self.key_config.push_force
+                {
+                    self.toggle_force();
+                } else if !self.pending
+                    && !self.input_cred.is_visible()
+                    && e == self.key_config.push_set_upstream
+                {
+                    self.toggle_set_upstream();
                 }
             }
             return Ok(true);
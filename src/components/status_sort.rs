@@ -0,0 +1,88 @@
+use crate::ui::style::SharedTheme;
+use asyncgit::StatusItemType;
+use serde::{Deserialize, Serialize};
+use tui::text::{Span, Spans};
+
+/// ordering applied to the entries of a status list; serializable
+/// so it can be persisted as a field on the app's config struct
+/// (alongside the existing key-bindings file) the same way
+/// `Theme` is persisted to `theme.ron`
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub enum StatusSortOrder {
+    /// alphabetical by path (the long-standing default)
+    Path,
+    /// grouped by `StatusItemType` priority, mirroring how prompt
+    /// tools surface conflicts and staged work first ("gitsort")
+    GitStatus,
+}
+
+impl Default for StatusSortOrder {
+    fn default() -> Self {
+        Self::Path
+    }
+}
+
+impl StatusSortOrder {
+    /// flips between the two orders; bound to a toggle key
+    /// (`key_config.status_sort_toggle`) by the status component
+    pub fn toggled(self) -> Self {
+        match self {
+            Self::Path => Self::GitStatus,
+            Self::GitStatus => Self::Path,
+        }
+    }
+}
+
+/// priority of a status entry when sorted by [`StatusSortOrder::GitStatus`];
+/// conflicted entries sort first, untracked last
+fn status_priority(
+    typ: StatusItemType,
+    conflicted: bool,
+    staged: bool,
+) -> u8 {
+    if conflicted {
+        return 0;
+    }
+
+    match typ {
+        StatusItemType::New if staged => 1,
+        StatusItemType::Modified => 2,
+        StatusItemType::Deleted => 3,
+        StatusItemType::Renamed => 4,
+        StatusItemType::Typechange => 5,
+        StatusItemType::New => 6,
+    }
+}
+
+/// sorts `items` in place by git-status priority, preserving the
+/// existing relative order of entries within the same group
+pub fn sort_by_status<T>(
+    items: &mut [T],
+    typ_of: impl Fn(&T) -> StatusItemType,
+    conflicted_of: impl Fn(&T) -> bool,
+    staged_of: impl Fn(&T) -> bool,
+) {
+    items.sort_by_key(|item| {
+        status_priority(
+            typ_of(item),
+            conflicted_of(item),
+            staged_of(item),
+        )
+    });
+}
+
+/// a short header line labelling one `StatusItemType` group in a
+/// `StatusSortOrder::GitStatus`-sorted list, styled via
+/// `Theme::item_header`
+pub fn group_header(
+    typ: StatusItemType,
+    label: &str,
+    theme: &SharedTheme,
+) -> Spans<'static> {
+    Spans::from(vec![Span::styled(
+        label.to_string(),
+        theme.item_header(typ),
+    )])
+}
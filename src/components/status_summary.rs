@@ -0,0 +1,134 @@
+use crate::ui::style::SharedTheme;
+use anyhow::Result;
+use asyncgit::{
+    sync::{self, status::StatusType, RepoPathRef},
+    StatusItemType,
+};
+use tui::text::{Span, Spans};
+
+/// counts of worktree/index entries by kind, used to render the
+/// status summary bar
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StatusCounts {
+    pub conflicted: usize,
+    pub staged: usize,
+    pub modified: usize,
+    pub untracked: usize,
+}
+
+impl StatusCounts {
+    pub fn add(&mut self, typ: StatusItemType, staged: bool) {
+        if staged {
+            self.staged += 1;
+            return;
+        }
+
+        match typ {
+            StatusItemType::Typechange
+            | StatusItemType::Modified
+            | StatusItemType::Renamed
+            | StatusItemType::Deleted => self.modified += 1,
+            StatusItemType::New => self.untracked += 1,
+        }
+    }
+}
+
+/// computed ahead/behind/stash state for the current branch,
+/// rendered as a compact one-line widget
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RepoSummary {
+    pub ahead: usize,
+    pub behind: usize,
+    pub has_stash: bool,
+    pub counts: StatusCounts,
+}
+
+impl RepoSummary {
+    pub fn fetch(repo: &RepoPathRef) -> Result<Self> {
+        let (ahead, behind) =
+            sync::branch_ahead_behind(repo).unwrap_or((0, 0));
+        let has_stash = sync::stash_list(repo)
+            .map(|stashes| !stashes.is_empty())
+            .unwrap_or_default();
+
+        let mut counts = StatusCounts {
+            conflicted: sync::conflict_count(repo)
+                .unwrap_or_default(),
+            ..StatusCounts::default()
+        };
+
+        for item in sync::get_status(repo, StatusType::Stage)? {
+            counts.add(item.status, true);
+        }
+        for item in sync::get_status(repo, StatusType::WorkingDir)?
+        {
+            counts.add(item.status, false);
+        }
+
+        Ok(Self {
+            ahead,
+            behind,
+            has_stash,
+            counts,
+        })
+    }
+
+    pub fn diverged(&self) -> bool {
+        self.ahead > 0 && self.behind > 0
+    }
+
+    /// renders the glyph line, e.g. `⇡2 ⇣1 ⇕ $ !3 +1`
+    pub fn spans(&self, theme: &SharedTheme) -> Spans {
+        let mut spans = Vec::new();
+
+        if self.ahead > 0 {
+            spans.push(Span::styled(
+                format!("⇡{} ", self.ahead),
+                theme.status_ahead(),
+            ));
+        }
+        if self.behind > 0 {
+            spans.push(Span::styled(
+                format!("⇣{} ", self.behind),
+                theme.status_behind(),
+            ));
+        }
+        if self.diverged() {
+            spans.push(Span::styled(
+                "⇕ ",
+                theme.status_diverged(),
+            ));
+        }
+
+        if self.has_stash {
+            spans.push(Span::styled("$ ", theme.status_stash()));
+        }
+
+        if self.counts.conflicted > 0 {
+            spans.push(Span::styled(
+                format!("!{} ", self.counts.conflicted),
+                theme.text_danger(),
+            ));
+        }
+
+        if self.counts.staged > 0 {
+            spans.push(Span::raw(format!("+{} ", self.counts.staged)));
+        }
+
+        if self.counts.modified > 0 {
+            spans.push(Span::raw(format!(
+                "~{} ",
+                self.counts.modified
+            )));
+        }
+
+        if self.counts.untracked > 0 {
+            spans.push(Span::raw(format!(
+                "?{} ",
+                self.counts.untracked
+            )));
+        }
+
+        Spans::from(spans)
+    }
+}
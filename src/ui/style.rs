@@ -7,15 +7,224 @@ use ron::{
 };
 use serde::{Deserialize, Serialize};
 use std::{
+    cell::RefCell,
+    collections::HashMap,
     fs::File,
     io::{Read, Write},
     path::PathBuf,
     rc::Rc,
 };
-use tui::style::{Color, Modifier, Style};
+use syntect::{
+    easy::HighlightLines,
+    highlighting::{
+        Color as SyntectColor, Style as SyntectStyle, ThemeSet,
+    },
+    parsing::SyntaxSet,
+};
+use tui::{
+    style::{Color, Modifier, Style},
+    text::Span,
+};
 
 pub type SharedTheme = Rc<Theme>;
 
+fn syntect_to_tui_color(color: SyntectColor) -> Color {
+    Color::Rgb(color.r, color.g, color.b)
+}
+
+/// a theme color field as written in `theme.ron`: either a plain
+/// tui `Color` (`Red`, `Rgb(10,20,30)`, `Indexed(5)`, ...), or a
+/// string that is resolved once the whole file has been parsed -
+/// a CSS-style hex code (`"#1f2430"`, `"#fff"`), an extended named
+/// color, or a reference into the top-level `palette` map
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ColorSpec {
+    Direct(Color),
+    Named(String),
+}
+
+impl ColorSpec {
+    fn resolve(
+        &self,
+        palette: &HashMap<String, Color>,
+    ) -> Result<Color> {
+        match self {
+            Self::Direct(color) => Ok(*color),
+            Self::Named(name) => parse_hex_color(name)
+                .or_else(|| palette.get(name).copied())
+                .or_else(|| parse_extended_color(name))
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "unknown theme color `{}` (not a hex code, \
+                         extended color name, or palette entry)",
+                        name
+                    )
+                }),
+        }
+    }
+}
+
+fn parse_hex_color(s: &str) -> Option<Color> {
+    let hex = s.strip_prefix('#')?;
+
+    // `hex.len()` below is a byte length, and the `6` arm slices by
+    // byte offset; reject non-ASCII input up front so a malformed,
+    // hand-edited `theme.ron` fails gracefully instead of panicking
+    // on a byte offset that lands inside a multi-byte character
+    if !hex.is_ascii() {
+        return None;
+    }
+
+    let expand = |c: u8| c * 16 + c;
+    let (r, g, b) = match hex.len() {
+        3 => {
+            let digits: Vec<u8> = hex
+                .chars()
+                .map(|c| c.to_digit(16).map(|d| d as u8))
+                .collect::<Option<_>>()?;
+            (
+                expand(digits[0]),
+                expand(digits[1]),
+                expand(digits[2]),
+            )
+        }
+        6 => {
+            let byte = |i: usize| {
+                u8::from_str_radix(&hex[i..i + 2], 16).ok()
+            };
+            (byte(0)?, byte(2)?, byte(4)?)
+        }
+        _ => return None,
+    };
+
+    Some(Color::Rgb(r, g, b))
+}
+
+/// a small set of extended named colors beyond the 16 ANSI names
+/// tui's `Color` already understands
+fn parse_extended_color(name: &str) -> Option<Color> {
+    Some(match name.to_ascii_lowercase().as_str() {
+        "orange" => Color::Rgb(255, 165, 0),
+        "pink" => Color::Rgb(255, 192, 203),
+        "purple" => Color::Rgb(128, 0, 128),
+        "teal" => Color::Rgb(0, 128, 128),
+        "brown" => Color::Rgb(165, 42, 42),
+        "navy" => Color::Rgb(0, 0, 128),
+        "gold" => Color::Rgb(255, 215, 0),
+        "salmon" => Color::Rgb(250, 128, 114),
+        "turquoise" => Color::Rgb(64, 224, 208),
+        "violet" => Color::Rgb(238, 130, 238),
+        _ => return None,
+    })
+}
+
+#[derive(Deserialize)]
+struct RawTheme {
+    #[serde(default)]
+    palette: HashMap<String, ColorSpec>,
+    selected_tab: ColorSpec,
+    command_fg: ColorSpec,
+    selection_bg: ColorSpec,
+    cmdbar_extra_lines_bg: ColorSpec,
+    disabled_fg: ColorSpec,
+    diff_line_add: ColorSpec,
+    diff_line_delete: ColorSpec,
+    diff_file_added: ColorSpec,
+    diff_file_removed: ColorSpec,
+    diff_file_moved: ColorSpec,
+    diff_file_modified: ColorSpec,
+    commit_hash: ColorSpec,
+    commit_time: ColorSpec,
+    commit_author: ColorSpec,
+    danger_fg: ColorSpec,
+    status_ahead: ColorSpec,
+    status_behind: ColorSpec,
+    status_diverged: ColorSpec,
+    status_stash: ColorSpec,
+    #[serde(default)]
+    syntax_theme: Option<String>,
+}
+
+impl RawTheme {
+    /// resolves every `palette` entry to a concrete `Color`,
+    /// repeating passes over the map so that a palette entry may
+    /// itself reference another palette entry regardless of the
+    /// (unordered) iteration order of the backing `HashMap`
+    fn resolve_palette(
+        palette_spec: &HashMap<String, ColorSpec>,
+    ) -> Result<HashMap<String, Color>> {
+        let mut resolved = HashMap::new();
+
+        for _ in 0..palette_spec.len() {
+            if resolved.len() == palette_spec.len() {
+                break;
+            }
+
+            for (name, spec) in palette_spec {
+                if resolved.contains_key(name) {
+                    continue;
+                }
+                if let Ok(color) = spec.resolve(&resolved) {
+                    resolved.insert(name.clone(), color);
+                }
+            }
+        }
+
+        for (name, spec) in palette_spec {
+            if !resolved.contains_key(name) {
+                // surfaces a real error (unknown/cyclic reference)
+                // instead of silently leaving the entry unresolved
+                resolved.insert(name.clone(), spec.resolve(&resolved)?);
+            }
+        }
+
+        Ok(resolved)
+    }
+
+    fn resolve(self) -> Result<Theme> {
+        let palette = Self::resolve_palette(&self.palette)?;
+
+        Ok(Theme {
+            selected_tab: self.selected_tab.resolve(&palette)?,
+            command_fg: self.command_fg.resolve(&palette)?,
+            selection_bg: self.selection_bg.resolve(&palette)?,
+            cmdbar_extra_lines_bg: self
+                .cmdbar_extra_lines_bg
+                .resolve(&palette)?,
+            disabled_fg: self.disabled_fg.resolve(&palette)?,
+            diff_line_add: self.diff_line_add.resolve(&palette)?,
+            diff_line_delete: self
+                .diff_line_delete
+                .resolve(&palette)?,
+            diff_file_added: self
+                .diff_file_added
+                .resolve(&palette)?,
+            diff_file_removed: self
+                .diff_file_removed
+                .resolve(&palette)?,
+            diff_file_moved: self
+                .diff_file_moved
+                .resolve(&palette)?,
+            diff_file_modified: self
+                .diff_file_modified
+                .resolve(&palette)?,
+            commit_hash: self.commit_hash.resolve(&palette)?,
+            commit_time: self.commit_time.resolve(&palette)?,
+            commit_author: self.commit_author.resolve(&palette)?,
+            danger_fg: self.danger_fg.resolve(&palette)?,
+            status_ahead: self.status_ahead.resolve(&palette)?,
+            status_behind: self.status_behind.resolve(&palette)?,
+            status_diverged: self
+                .status_diverged
+                .resolve(&palette)?,
+            status_stash: self.status_stash.resolve(&palette)?,
+            syntax_theme: self.syntax_theme,
+            syntax_cache: RefCell::new(None),
+        })
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Theme {
     selected_tab: Color,
@@ -47,6 +256,33 @@ pub struct Theme {
     commit_author: Color,
     #[serde(with = "Color")]
     danger_fg: Color,
+    #[serde(with = "Color")]
+    status_ahead: Color,
+    #[serde(with = "Color")]
+    status_behind: Color,
+    #[serde(with = "Color")]
+    status_diverged: Color,
+    #[serde(with = "Color")]
+    status_stash: Color,
+    /// name of a syntect theme (bundled, or a `.tmTheme` file next
+    /// to `theme.ron`) used to syntax highlight diff hunk bodies;
+    /// `None` keeps the flat add/delete coloring below
+    #[serde(default)]
+    syntax_theme: Option<String>,
+    /// lazily built the first time a syntax-highlighted line is
+    /// requested; the outer `Option` distinguishes "not attempted
+    /// yet" from "attempted and failed" (e.g. a misconfigured
+    /// `syntax_theme` name) so a bad config disables highlighting
+    /// once instead of retrying the full syntect load on every line
+    #[serde(skip)]
+    syntax_cache: RefCell<Option<Option<SyntaxCache>>>,
+}
+
+#[derive(Debug)]
+struct SyntaxCache {
+    set: SyntaxSet,
+    theme: syntect::highlighting::Theme,
+    lines: HashMap<(String, String), Vec<(SyntectStyle, String)>>,
 }
 
 impl Theme {
@@ -129,12 +365,21 @@ impl Theme {
             StatusItemType::Renamed => {
                 Style::default().fg(self.diff_file_moved)
             }
-            StatusItemType::Typechange => Style::default(),
+            StatusItemType::Typechange => {
+                Style::default().fg(self.diff_file_modified)
+            }
         };
 
         self.apply_select(style, selected)
     }
 
+    /// a subtle header style for a group of status entries sharing
+    /// a `StatusItemType`, reusing the same color as [`Theme::item`]
+    /// without the foreground emphasis
+    pub fn item_header(&self, typ: StatusItemType) -> Style {
+        self.item(typ, false).add_modifier(Modifier::DIM)
+    }
+
     fn apply_select(&self, style: Style, selected: bool) -> Style {
         if selected {
             style.bg(self.selection_bg)
@@ -184,6 +429,117 @@ impl Theme {
         self.apply_select(style, selected)
     }
 
+    /// renders a single diff body line, syntax highlighted by
+    /// `extension` when a `syntax_theme` is configured, with the
+    /// highlighted foreground spans blended onto the add/delete
+    /// background; falls back to the flat `diff_line` coloring when
+    /// no syntax theme is set or the extension is unknown.
+    /// the diff-hunk-rendering component should call this per line
+    /// instead of `diff_line` to get syntax highlighting
+    pub fn diff_line_highlighted(
+        &self,
+        typ: DiffLineType,
+        selected: bool,
+        extension: &str,
+        content: &str,
+    ) -> Vec<Span<'static>> {
+        let fallback = || {
+            vec![Span::styled(
+                content.to_string(),
+                self.diff_line(typ, selected),
+            )]
+        };
+
+        if self.syntax_theme.is_none() {
+            return fallback();
+        }
+
+        let bg = match typ {
+            DiffLineType::Add => Some(self.diff_line_add),
+            DiffLineType::Delete => Some(self.diff_line_delete),
+            DiffLineType::Header | DiffLineType::None => None,
+        };
+
+        let mut cache = self.syntax_cache.borrow_mut();
+        let cache = cache
+            .get_or_insert_with(|| self.load_syntax_cache())
+            .as_mut();
+
+        let cache = match cache {
+            Some(cache) => cache,
+            None => return fallback(),
+        };
+
+        let key = (extension.to_string(), content.to_string());
+        if !cache.lines.contains_key(&key) {
+            let highlighted = match Self::highlight_line(
+                &cache.set,
+                &cache.theme,
+                extension,
+                content,
+            ) {
+                Some(highlighted) => highlighted,
+                None => return fallback(),
+            };
+            cache.lines.insert(key.clone(), highlighted);
+        }
+
+        cache.lines[&key]
+            .iter()
+            .map(|(style, text)| {
+                let mut tui_style =
+                    Style::default().fg(syntect_to_tui_color(
+                        style.foreground,
+                    ));
+                if let Some(bg) = bg {
+                    tui_style = tui_style.bg(bg);
+                }
+                tui_style = self.apply_select(tui_style, selected);
+                Span::styled(text.clone(), tui_style)
+            })
+            .collect()
+    }
+
+    fn highlight_line(
+        set: &SyntaxSet,
+        theme: &syntect::highlighting::Theme,
+        extension: &str,
+        content: &str,
+    ) -> Option<Vec<(SyntectStyle, String)>> {
+        let syntax = set.find_syntax_by_extension(extension)?;
+        let mut highlighter = HighlightLines::new(syntax, theme);
+        let ranges =
+            highlighter.highlight_line(content, set).ok()?;
+        Some(
+            ranges
+                .into_iter()
+                .map(|(style, text)| (style, text.to_string()))
+                .collect(),
+        )
+    }
+
+    fn load_syntax_cache(&self) -> Option<SyntaxCache> {
+        let name = self.syntax_theme.as_ref()?;
+        let set = SyntaxSet::load_defaults_newlines();
+        let theme_set = ThemeSet::load_defaults();
+
+        let theme = match theme_set.themes.get(name) {
+            Some(theme) => theme.clone(),
+            None => {
+                let path = Self::get_theme_file()
+                    .ok()?
+                    .with_file_name(name);
+                ThemeSet::get_theme(path).ok()?
+            }
+        };
+
+        Some(SyntaxCache {
+            set,
+            theme,
+            lines: HashMap::new(),
+        })
+    }
+
     pub fn text_danger(&self) -> Style {
         Style::default().fg(self.danger_fg)
     }
@@ -220,6 +576,24 @@ impl Theme {
         )
     }
 
+    pub fn status_ahead(&self) -> Style {
+        Style::default().fg(self.status_ahead)
+    }
+
+    pub fn status_behind(&self) -> Style {
+        Style::default().fg(self.status_behind)
+    }
+
+    pub fn status_diverged(&self) -> Style {
+        Style::default()
+            .fg(self.status_diverged)
+            .add_modifier(Modifier::BOLD)
+    }
+
+    pub fn status_stash(&self) -> Style {
+        Style::default().fg(self.status_stash)
+    }
+
     fn save(&self) -> Result<()> {
         let theme_file = Self::get_theme_file()?;
         let mut file = File::create(theme_file)?;
@@ -237,7 +611,8 @@ impl Theme {
         let mut f = File::open(theme_file)?;
         let mut buffer = Vec::new();
         f.read_to_end(&mut buffer)?;
-        Ok(from_bytes(&buffer)?)
+        let raw: RawTheme = from_bytes(&buffer)?;
+        raw.resolve()
     }
 
     fn init_internal() -> Result<Self> {
@@ -276,6 +651,12 @@ impl Default for Theme {
             commit_time: Color::LightCyan,
             commit_author: Color::Green,
             danger_fg: Color::Red,
+            status_ahead: Color::Green,
+            status_behind: Color::Red,
+            status_diverged: Color::Yellow,
+            status_stash: Color::Cyan,
+            syntax_theme: None,
+            syntax_cache: RefCell::new(None),
         }
     }
 }